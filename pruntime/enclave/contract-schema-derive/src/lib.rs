@@ -0,0 +1,51 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Derives `contracts::schema::DescribeSchema` for an enum by walking its
+/// variants and fields at compile time. The schema returned by
+/// `HasSchema::schema` is generated straight from the `Command`/`Request`/
+/// `Response`/`Error` definitions it describes, so it can't drift from them
+/// the way a hand-written copy could.
+#[proc_macro_derive(DescribeSchema)]
+pub fn derive_describe_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident;
+
+    let data = match input.data {
+        Data::Enum(data) => data,
+        _ => panic!("DescribeSchema can only be derived for enums"),
+    };
+
+    let variants = data.variants.into_iter().map(|variant| {
+        let variant_name = variant.ident.to_string();
+        let fields: Vec<_> = match variant.fields {
+            Fields::Named(fields) => fields.named.into_iter().map(|field| {
+                let field_name = field.ident.expect("named field has a name").to_string();
+                let ty = field.ty;
+                quote! { contracts::schema::field(#field_name, stringify!(#ty)) }
+            }).collect(),
+            Fields::Unnamed(fields) => fields.unnamed.into_iter().enumerate().map(|(index, field)| {
+                let field_name = index.to_string();
+                let ty = field.ty;
+                quote! { contracts::schema::field(#field_name, stringify!(#ty)) }
+            }).collect(),
+            Fields::Unit => Vec::new(),
+        };
+        quote! {
+            contracts::schema::variant(#variant_name, crate::std::vec::Vec::from([#(#fields),*]))
+        }
+    });
+
+    let expanded = quote! {
+        impl contracts::schema::DescribeSchema for #name {
+            fn variants() -> crate::std::vec::Vec<contracts::schema::VariantSchema> {
+                crate::std::vec::Vec::from([#(#variants),*])
+            }
+        }
+    };
+
+    expanded.into()
+}
@@ -1,30 +1,57 @@
 use serde::{Serialize, Deserialize};
 
 use crate::contracts;
-use crate::contracts::{AccountIdWrapper};
+use crate::contracts::{AccountIdWrapper, Balance, ContractKey, Event, EventLog, Hash, Ledger, MessageInfo};
+use crate::contracts::schema::{ContractSchema, DescribeSchema, HasSchema};
 use crate::types::TxRef;
 use crate::TransactionStatus;
 use crate::std::collections::BTreeMap;
 use crate::std::string::String;
+use crate::std::vec::Vec;
 
 /// HelloWorld contract states.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct HelloWorld {
-    whisper: BTreeMap<AccountIdWrapper, String>
+    whisper: BTreeMap<AccountIdWrapper, String>,
+    /// Funds tipped to the contract alongside a `SetWhisper` command.
+    funds: Ledger,
+    /// Events emitted by `SetWhisper`.
+    events: EventLog,
+    /// This instance's long-lived confidential-query keypair. Part of the
+    /// contract's serialized state so it survives a checkpoint/reload
+    /// instead of rotating and breaking every session a client already
+    /// started against the old public key.
+    key: ContractKey,
+}
+
+/// The message a deployer passes in when the contract is registered.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitMsg {
+    /// An optional whisper stored as the deployer's own whisper. Other
+    /// accounts are not given it.
+    pub default_whisper: Option<String>,
 }
 
 /// The commands that the contract accepts from the blockchain. Also called transactions.
 /// Commands are supposed to update the states of the contract.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, DescribeSchema)]
 pub enum Command {
     /// Increments the whisper in the contract by some number
     SetWhisper {
         whisper: String,
     },
+    /// Pays out funds tipped to the contract by the sender
+    Withdraw {
+        to: AccountIdWrapper,
+        amount: Balance,
+    },
 }
 
-/// The errors that the contract could throw for some queries
-#[derive(Serialize, Deserialize, Debug)]
+/// The errors that the contract could throw for some queries. Command
+/// failures (e.g. an overdrawn `Withdraw`) are surfaced through
+/// `TransactionStatus` instead, since `handle_command` doesn't return a
+/// `Response` for this to travel in.
+#[derive(Serialize, Deserialize, Debug, DescribeSchema)]
 pub enum Error {
     NotAuthorized,
     SomeOtherError,
@@ -32,19 +59,46 @@ pub enum Error {
 
 /// Query requests. The end users can only query the contract states by sending requests.
 /// Queries are not supposed to write to the contract states.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, DescribeSchema)]
 pub enum Request {
     /// Ask for the value of the whisper
     GetWhisper,
+    /// Ask for the funds tipped to the contract by an account
+    Balance {
+        account: AccountIdWrapper,
+    },
+    /// Ask for the events emitted so far, optionally filtered by topic and
+    /// bounded to a range of positions in the event log
+    GetEvents {
+        topic0: Option<Hash>,
+        topic1: Option<AccountIdWrapper>,
+        from_seq: Option<u64>,
+        to_seq: Option<u64>,
+    },
+    /// Ask for the contract's confidential-query public key. Unauthenticated:
+    /// this is how a client starts the encrypted-query handshake.
+    GetPublicKey,
 }
 
 /// Query responses.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, DescribeSchema)]
 pub enum Response {
     /// Returns the value of the whisper
     GetWhisper {
         whisper: String,
     },
+    /// Returns the funds tipped to the contract by an account
+    Balance {
+        balance: Balance,
+    },
+    /// Returns the events matching a `GetEvents` request, in order
+    GetEvents {
+        events: Vec<Event>,
+    },
+    /// Returns the contract's confidential-query public key
+    PublicKey {
+        public_key: [u8; 32],
+    },
     /// Something wrong happened
     Error(Error)
 }
@@ -57,22 +111,65 @@ impl HelloWorld {
     }
 }
 
+impl HasSchema for HelloWorld {
+    fn schema() -> ContractSchema {
+        ContractSchema {
+            command: Command::variants(),
+            request: Request::variants(),
+            response: Response::variants(),
+            error: Error::variants(),
+        }
+    }
+}
+
 impl contracts::Contract<Command, Request, Response> for HelloWorld {
+    type InitMsg = InitMsg;
+
     // Returns the contract id
     fn id(&self) -> contracts::ContractId { contracts::HELLO_WORLD }
 
+    // Returns this instance's confidential-query keypair.
+    fn contract_key(&self) -> &ContractKey { &self.key }
+
+    // Seeds the deployer-supplied default whisper, if any. Called once when the
+    // contract is registered, before it handles any command or query.
+    fn instantiate(&mut self, origin: &chain::AccountId, msg: InitMsg) -> TransactionStatus {
+        if let Some(default_whisper) = msg.default_whisper {
+            let deployer = AccountIdWrapper(origin.clone());
+            self.whisper.insert(deployer, default_whisper);
+        }
+        TransactionStatus::Ok
+    }
+
     // Handles the commands from transactions on the blockchain. This method doesn't respond.
-    fn handle_command(&mut self, _origin: &chain::AccountId, _txref: &TxRef, cmd: Command) -> TransactionStatus {
+    fn handle_command(&mut self, _origin: &chain::AccountId, _txref: &TxRef, cmd: Command, info: MessageInfo) -> TransactionStatus {
         match cmd {
             // Handle the `Increment` command with one parameter
             Command::SetWhisper { whisper } => {
                 // Get the current user
                 let current_user = AccountIdWrapper(_origin.clone());
+                // Credit any funds tipped alongside this whisper.
+                if info.sent_funds > 0 {
+                    self.funds.credit(&current_user, info.sent_funds);
+                }
+                // Emit an event keyed on the author.
+                self.events.emit(
+                    Vec::from([contracts::account_topic(&current_user)]),
+                    whisper.clone().into_bytes(),
+                    _txref.clone(),
+                );
                 // Set the whisper value against the user.
                 self.whisper.insert(current_user, whisper);
                 // Returns TransactionStatus::Ok to indicate a successful transaction
                 TransactionStatus::Ok
             },
+            Command::Withdraw { to, amount } => {
+                let current_user = AccountIdWrapper(_origin.clone());
+                match self.funds.transfer(&current_user, &to, amount) {
+                    Ok(()) => TransactionStatus::Ok,
+                    Err(()) => TransactionStatus::InsufficientBalance,
+                }
+            },
         }
     }
 
@@ -97,6 +194,19 @@ impl contracts::Contract<Command, Request, Response> for HelloWorld {
                     }
                     Err(Error::NotAuthorized)
                 },
+                // Handle the `Balance` request.
+                Request::Balance { account } => {
+                    Ok(Response::Balance { balance: self.funds.balance_of(&account) })
+                },
+                // Handle the `GetEvents` request.
+                Request::GetEvents { topic0, topic1, from_seq, to_seq } => {
+                    let topic1 = topic1.map(|account| contracts::account_topic(&account));
+                    Ok(Response::GetEvents { events: self.events.query(topic0, topic1, from_seq, to_seq) })
+                },
+                // Handle the `GetPublicKey` request. Unauthenticated on purpose.
+                Request::GetPublicKey => {
+                    Ok(Response::PublicKey { public_key: self.key.public_key() })
+                },
             }
         };
         match inner() {
@@ -106,3 +216,32 @@ impl contracts::Contract<Command, Request, Response> for HelloWorld {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contracts::Contract;
+
+    fn account(seed: u8) -> chain::AccountId {
+        chain::AccountId::from([seed; 32])
+    }
+
+    #[test]
+    fn instantiate_seeds_the_default_whisper() {
+        let mut contract = HelloWorld::new();
+        let deployer = account(1);
+        contract.instantiate(&deployer, InitMsg { default_whisper: Some("hi".into()) });
+        assert_eq!(
+            contract.whisper.get(&AccountIdWrapper(deployer)),
+            Some(&String::from("hi")),
+        );
+    }
+
+    #[test]
+    fn instantiate_without_a_default_whisper_is_a_no_op() {
+        let mut contract = HelloWorld::new();
+        let deployer = account(1);
+        contract.instantiate(&deployer, InitMsg { default_whisper: None });
+        assert!(contract.whisper.is_empty());
+    }
+}
+
@@ -0,0 +1,227 @@
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use core::fmt::Debug;
+use parity_scale_codec::Encode;
+
+use crate::types::TxRef;
+use crate::TransactionStatus;
+use crate::std::collections::BTreeMap;
+use crate::std::vec::Vec;
+
+pub mod helloworld;
+pub mod query_crypto;
+pub mod schema;
+
+pub use query_crypto::{ContractKey, EncryptedQuery, EncryptedResponse};
+
+/// Identifies a deployed contract instance.
+pub type ContractId = u32;
+
+/// The native token amount a command can carry, or a contract can hold.
+pub type Balance = u128;
+
+/// A topic an event can be indexed by, e.g. the hash of an account id.
+pub type Hash = chain::Hash;
+
+pub const HELLO_WORLD: ContractId = 8;
+
+/// Wraps a chain account id so it can be used as a map key (`Ord`, `Eq`) and
+/// (de)serialized across the enclave boundary.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct AccountIdWrapper(pub chain::AccountId);
+
+/// The native funds attached to a command, mirroring CosmWasm's `MessageInfo`.
+/// The sender is already given to `handle_command` as `origin`, so it isn't
+/// repeated here.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MessageInfo {
+    pub sent_funds: Balance,
+}
+
+/// A per-contract native-funds ledger. Contracts that accept value alongside
+/// their commands hold one of these in their state and credit it from
+/// `handle_command`, using `transfer` to pay balances back out.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Ledger {
+    balances: BTreeMap<AccountIdWrapper, Balance>,
+}
+
+impl Ledger {
+    /// Credits `account` with `amount`, e.g. the funds attached to a command.
+    pub fn credit(&mut self, account: &AccountIdWrapper, amount: Balance) {
+        let balance = self.balances.entry(account.clone()).or_insert(0);
+        *balance += amount;
+    }
+
+    /// Moves `amount` from `from` to `to`. Fails if `from` doesn't hold enough.
+    pub fn transfer(&mut self, from: &AccountIdWrapper, to: &AccountIdWrapper, amount: Balance) -> Result<(), ()> {
+        let from_balance = self.balances.get(from).copied().unwrap_or(0);
+        if from_balance < amount {
+            return Err(());
+        }
+        self.balances.insert(from.clone(), from_balance - amount);
+        self.credit(to, amount);
+        Ok(())
+    }
+
+    /// Returns the balance held for `account`, or zero if it has none.
+    pub fn balance_of(&self, account: &AccountIdWrapper) -> Balance {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+}
+
+/// Hashes an account id down to an event topic.
+pub fn account_topic(account: &AccountIdWrapper) -> Hash {
+    Hash::from(sp_core::blake2_256(&account.0.encode()))
+}
+
+/// One entry appended to a contract's `EventLog` by `emit`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Event {
+    pub topics: Vec<Hash>,
+    pub data: Vec<u8>,
+    pub tx: TxRef,
+    /// This event's index in the log. Not a chain tx/block number — just the
+    /// cursor `EventLog::query`'s `from_seq`/`to_seq` range over.
+    pub seq: u64,
+}
+
+/// An append-only, topic-indexed log of events a contract has emitted.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct EventLog {
+    events: Vec<Event>,
+}
+
+impl EventLog {
+    /// Appends an event keyed by `topics` to the log.
+    pub fn emit(&mut self, topics: Vec<Hash>, data: Vec<u8>, tx: TxRef) {
+        let seq = self.events.len() as u64;
+        self.events.push(Event { topics, data, tx, seq });
+    }
+
+    /// Returns events whose topics match `topic0`/`topic1` (when given) and
+    /// whose log index falls within `[from_seq, to_seq]`. `from_seq`/`to_seq`
+    /// bound position in this log, not a chain tx or block number.
+    pub fn query(
+        &self,
+        topic0: Option<Hash>,
+        topic1: Option<Hash>,
+        from_seq: Option<u64>,
+        to_seq: Option<u64>,
+    ) -> Vec<Event> {
+        self.events.iter()
+            .filter(|event| from_seq.map_or(true, |from| event.seq >= from))
+            .filter(|event| to_seq.map_or(true, |to| event.seq <= to))
+            .filter(|event| topic0.map_or(true, |topic| event.topics.get(0) == Some(&topic)))
+            .filter(|event| topic1.map_or(true, |topic| event.topics.get(1) == Some(&topic)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A confidential smart contract running inside the enclave.
+///
+/// `InitMsg` carries the data a deployer supplies when the contract is
+/// registered, `Cmd` is the set of commands accepted from transactions, `Req`
+/// is the set of read-only queries, and `Resp` is what a query answers with.
+pub trait Contract<Cmd, Req, Resp>
+where
+    Cmd: Serialize + DeserializeOwned + Debug,
+    Req: Serialize + DeserializeOwned + Debug,
+    Resp: Serialize + DeserializeOwned + Debug,
+{
+    /// The message a deployer passes in when the contract is first registered.
+    type InitMsg: Serialize + DeserializeOwned + Debug;
+
+    // Returns the contract id
+    fn id(&self) -> ContractId;
+
+    /// Initializes the contract state from the deployer-supplied `msg`. Called
+    /// exactly once, before the contract handles its first command or query,
+    /// so it's the right place to capture the deployer account, validate
+    /// admin lists, or seed default state.
+    fn instantiate(&mut self, origin: &chain::AccountId, msg: Self::InitMsg) -> TransactionStatus;
+
+    // Handles the commands from transactions on the blockchain. This method doesn't respond.
+    // `info` carries any native funds attached to the transaction; the sender is `origin`.
+    fn handle_command(&mut self, origin: &chain::AccountId, txref: &TxRef, cmd: Cmd, info: MessageInfo) -> TransactionStatus;
+
+    // Handles a direct query and responds to the query. It shouldn't modify the contract states.
+    fn handle_query(&mut self, origin: Option<&chain::AccountId>, req: Req) -> Resp;
+
+    /// Returns the contract's long-lived X25519 key, used to answer an
+    /// unauthenticated "get my public key" query and to service
+    /// `handle_encrypted_query`.
+    fn contract_key(&self) -> &ContractKey;
+
+    /// Handles an end-to-end encrypted query: decrypts `query` under the
+    /// secret shared with the client, dispatches it through `handle_query`,
+    /// then encrypts the response back under the same secret with a fresh
+    /// nonce. This keeps a confidential `Req`/`Resp` hidden from the gateway
+    /// that merely relays the envelope between client and enclave.
+    fn handle_encrypted_query(&mut self, origin: Option<&chain::AccountId>, query: &EncryptedQuery) -> Result<EncryptedResponse, query_crypto::CryptoError> {
+        let shared = query_crypto::shared_key(self.contract_key(), &query.client_pubkey);
+        let req: Req = query_crypto::decrypt_query(&shared, query)?;
+        let resp = self.handle_query(origin, req);
+        query_crypto::encrypt_response(&shared, &resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(seed: u8) -> AccountIdWrapper {
+        AccountIdWrapper(chain::AccountId::from([seed; 32]))
+    }
+
+    #[test]
+    fn ledger_credit_accumulates() {
+        let mut ledger = Ledger::default();
+        let alice = account(1);
+        ledger.credit(&alice, 10);
+        ledger.credit(&alice, 5);
+        assert_eq!(ledger.balance_of(&alice), 15);
+    }
+
+    #[test]
+    fn ledger_transfer_moves_funds() {
+        let mut ledger = Ledger::default();
+        let alice = account(1);
+        let bob = account(2);
+        ledger.credit(&alice, 10);
+        assert!(ledger.transfer(&alice, &bob, 4).is_ok());
+        assert_eq!(ledger.balance_of(&alice), 6);
+        assert_eq!(ledger.balance_of(&bob), 4);
+    }
+
+    #[test]
+    fn ledger_transfer_rejects_insufficient_funds() {
+        let mut ledger = Ledger::default();
+        let alice = account(1);
+        let bob = account(2);
+        ledger.credit(&alice, 3);
+        assert!(ledger.transfer(&alice, &bob, 4).is_err());
+        assert_eq!(ledger.balance_of(&alice), 3);
+        assert_eq!(ledger.balance_of(&bob), 0);
+    }
+
+    #[test]
+    fn event_log_query_filters_by_topic_and_seq() {
+        let mut log = EventLog::default();
+        let alice_topic = account_topic(&account(1));
+        let bob_topic = account_topic(&account(2));
+        log.emit(Vec::from([alice_topic]), Vec::from([1u8]), TxRef::default());
+        log.emit(Vec::from([bob_topic]), Vec::from([2u8]), TxRef::default());
+        log.emit(Vec::from([alice_topic]), Vec::from([3u8]), TxRef::default());
+
+        let alice_events = log.query(Some(alice_topic), None, None, None);
+        assert_eq!(alice_events.len(), 2);
+        assert_eq!(alice_events[0].data, Vec::from([1u8]));
+        assert_eq!(alice_events[1].data, Vec::from([3u8]));
+
+        let bounded = log.query(None, None, Some(1), Some(1));
+        assert_eq!(bounded.len(), 1);
+        assert_eq!(bounded[0].data, Vec::from([2u8]));
+    }
+}
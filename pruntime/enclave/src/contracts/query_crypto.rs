@@ -0,0 +1,184 @@
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::de::DeserializeOwned;
+use x25519_dalek::{StaticSecret, PublicKey};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, NewAead};
+use sgx_trts::trts::rsgx_read_rand;
+
+use crate::std::vec::Vec;
+
+/// Fills `buf` with randomness read directly from the enclave's hardware RNG
+/// (RDRAND, via `rsgx_read_rand`), never an OS-level source outside the TEE.
+fn fill_enclave_rand(buf: &mut [u8]) {
+    rsgx_read_rand(buf).expect("enclave RNG (RDRAND) must succeed");
+}
+
+fn random_key_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    fill_enclave_rand(&mut seed);
+    seed
+}
+
+fn random_nonce() -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    fill_enclave_rand(&mut nonce);
+    nonce
+}
+
+/// A confidential query, end-to-end encrypted from the client to the enclave
+/// holding the contract's secret key. The client generates a fresh X25519
+/// keypair per query; `client_pubkey` is its public half.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedQuery {
+    pub client_pubkey: [u8; 32],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+/// The encrypted counterpart to `EncryptedQuery`, sealed back to the client
+/// under the same shared secret with a fresh nonce.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedResponse {
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum CryptoError {
+    DecryptionFailed,
+    EncryptionFailed,
+    Serde,
+}
+
+/// A contract's long-lived X25519 keypair, used to establish a confidential
+/// channel with clients for `EncryptedQuery`/`EncryptedResponse`. This is
+/// part of the contract's serialized state (not `#[serde(skip)]`): a client
+/// that has already fetched the public half via `GetPublicKey` must still be
+/// able to reach the same key after the contract is checkpointed and
+/// reloaded, or every open session would decrypt-fail against a freshly
+/// rotated key.
+pub struct ContractKey {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl ContractKey {
+    pub fn generate() -> Self {
+        let secret = StaticSecret::from(random_key_seed());
+        let public = PublicKey::from(&secret);
+        ContractKey { secret, public }
+    }
+
+    /// The public half of this key, safe to hand out through an
+    /// unauthenticated query so clients can establish the channel.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+}
+
+impl Default for ContractKey {
+    /// Only used the first time a contract is instantiated. Every subsequent
+    /// load goes through `Deserialize`, which reconstructs the same secret.
+    fn default() -> Self {
+        Self::generate()
+    }
+}
+
+impl core::fmt::Debug for ContractKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ContractKey").field("public", &self.public).finish()
+    }
+}
+
+impl Serialize for ContractKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.secret.to_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContractKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let secret_bytes = <[u8; 32]>::deserialize(deserializer)?;
+        let secret = StaticSecret::from(secret_bytes);
+        let public = PublicKey::from(&secret);
+        Ok(ContractKey { secret, public })
+    }
+}
+
+/// Derives the symmetric key shared between `key` and `their_pubkey` via
+/// ECDH + HKDF. Computed up front (rather than threaded through
+/// `decrypt_query`/`encrypt_response` as `&ContractKey`) so a caller can drop
+/// the borrow on the owning contract before calling back into `handle_query`.
+pub fn shared_key(key: &ContractKey, their_pubkey: &[u8; 32]) -> [u8; 32] {
+    let their_public = PublicKey::from(*their_pubkey);
+    let shared_secret = key.secret.diffie_hellman(&their_public);
+    let hk = Hkdf::<Sha256>::new(None, shared_secret.as_bytes());
+    let mut okm = [0u8; 32];
+    hk.expand(b"phala-query-channel", &mut okm)
+        .expect("32 bytes is a valid length for Sha256 HKDF output");
+    okm
+}
+
+/// Decrypts an `EncryptedQuery` into a plaintext `Request` using the shared
+/// secret established with the client.
+pub fn decrypt_query<Req: DeserializeOwned>(shared: &[u8; 32], query: &EncryptedQuery) -> Result<Req, CryptoError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(shared));
+    let nonce = Nonce::from_slice(&query.nonce);
+    let plaintext = cipher.decrypt(nonce, query.ciphertext.as_ref())
+        .map_err(|_| CryptoError::DecryptionFailed)?;
+    serde_cbor::from_slice(&plaintext).map_err(|_| CryptoError::Serde)
+}
+
+/// Encrypts a plaintext `Response` back to the client under `shared`, using a
+/// fresh nonce.
+pub fn encrypt_response<Resp: Serialize>(shared: &[u8; 32], resp: &Resp) -> Result<EncryptedResponse, CryptoError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(shared));
+    let nonce_bytes = random_nonce();
+    let plaintext = serde_cbor::to_vec(resp).map_err(|_| CryptoError::Serde)?;
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_ref())
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    Ok(EncryptedResponse { nonce: nonce_bytes, ciphertext })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::string::String;
+
+    #[test]
+    fn ecdh_shared_key_is_symmetric() {
+        let contract = ContractKey::generate();
+        let client = ContractKey::generate();
+        assert_eq!(
+            shared_key(&contract, &client.public_key()),
+            shared_key(&client, &contract.public_key()),
+        );
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_round_trips() {
+        let contract = ContractKey::generate();
+        let client = ContractKey::generate();
+        let shared = shared_key(&contract, &client.public_key());
+
+        let original: String = "hello".into();
+        let encrypted = encrypt_response(&shared, &original).expect("encryption must succeed");
+        let decrypted: String = decrypt_query(&shared, &EncryptedQuery {
+            client_pubkey: client.public_key(),
+            nonce: encrypted.nonce,
+            ciphertext: encrypted.ciphertext,
+        }).expect("decryption must succeed");
+
+        assert_eq!(decrypted, original);
+    }
+
+    #[test]
+    fn contract_key_survives_a_serde_round_trip() {
+        let original = ContractKey::generate();
+        let bytes = serde_cbor::to_vec(&original).expect("serialization must succeed");
+        let reloaded: ContractKey = serde_cbor::from_slice(&bytes).expect("deserialization must succeed");
+        assert_eq!(original.public_key(), reloaded.public_key());
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Serialize, Deserialize};
+
+pub use contract_schema_derive::DescribeSchema;
+
+use crate::std::string::String;
+use crate::std::vec::Vec;
+
+/// One named, typed field of a variant, e.g. `whisper: String`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+/// One variant of a `Command`/`Request`/`Response`/`Error` enum.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VariantSchema {
+    pub name: &'static str,
+    pub fields: Vec<FieldSchema>,
+}
+
+/// A JSON-describable schema for a contract's message enums, analogous to a
+/// contract ABI: enough for off-chain tooling to build and validate `Command`
+/// and `Request` messages, and to know the shape of a `Response`, without
+/// reading the contract's source.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContractSchema {
+    pub command: Vec<VariantSchema>,
+    pub request: Vec<VariantSchema>,
+    pub response: Vec<VariantSchema>,
+    pub error: Vec<VariantSchema>,
+}
+
+/// Shorthand for describing one field of a variant.
+pub fn field(name: &'static str, ty: &'static str) -> FieldSchema {
+    FieldSchema { name, ty }
+}
+
+/// Shorthand for describing one variant of an enum.
+pub fn variant(name: &'static str, fields: Vec<FieldSchema>) -> VariantSchema {
+    VariantSchema { name, fields }
+}
+
+/// Implemented by each contract to describe its `Command`, `Request`,
+/// `Response`, and `Error` enums, so clients can generate typed bindings
+/// instead of hand-coding JSON against the contract's source. This trait only
+/// builds the schema; no runtime RPC that serves it to clients is defined here.
+pub trait HasSchema {
+    fn schema() -> ContractSchema;
+}
+
+/// Implemented via `#[derive(DescribeSchema)]` for each of a contract's
+/// `Command`/`Request`/`Response`/`Error` enums; see that macro's doc comment
+/// for why.
+pub trait DescribeSchema {
+    fn variants() -> Vec<VariantSchema>;
+}